@@ -18,6 +18,27 @@ pub struct Cli {
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub listen: String,
+    pub routes: Vec<RouteConfig>,
+
+    /// Upper bound on connections being handshaked/proxied at once. The
+    /// accept loop blocks on a semaphore once this many are in flight,
+    /// applying backpressure instead of spawning without limit.
+    #[serde(default = "default_max_concurrent_connections")]
+    pub max_concurrent_connections: usize,
+}
+
+fn default_max_concurrent_connections() -> usize {
+    1024
+}
+
+/// One virtual host served off the shared listener: a TLS identity keyed by
+/// SNI hostname, plus the upstream it proxies to once terminated.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RouteConfig {
+    /// SNI hostname to match against `ClientHello::server_name()`. The
+    /// literal value `"*"` marks the default route, used when the client
+    /// sends no SNI or an SNI that matches nothing else.
+    pub sni: String,
     pub upstream: String,
     pub tls: TlsConfig,
 }
@@ -29,6 +50,30 @@ pub struct TlsConfig {
     pub server_key: String,
     pub client_cert: String,
     pub client_key: String,
+
+    /// Peer identities (SAN dNSName or CN) allowed to connect, e.g.
+    /// `["client.internal.example.com", "*.workers.example.com"]`.
+    /// Empty means "any cert that chains to the CA", matching the old
+    /// behaviour.
+    #[serde(default)]
+    pub allowed_identities: Vec<String>,
+
+    /// ALPN protocols to offer/accept, most preferred first. Defaults to
+    /// `["h2", "http/1.1"]` so the sidecar doesn't silently break clients or
+    /// upstreams that only speak HTTP/1.1.
+    #[serde(default = "default_alpn")]
+    pub alpn: Vec<String>,
+
+    /// Hostname to present as SNI / validate the upstream certificate
+    /// against, when it must differ from the host dialed in `upstream`
+    /// (e.g. connecting by IP but validating a DNS name). Defaults to the
+    /// host parsed out of `upstream`.
+    #[serde(default)]
+    pub upstream_sni: Option<String>,
+}
+
+fn default_alpn() -> Vec<String> {
+    vec!["h2".to_string(), "http/1.1".to_string()]
 }
 
 
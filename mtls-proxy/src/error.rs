@@ -0,0 +1,30 @@
+//! The failure classes the proxy actually hits on the connection hot path,
+//! so callers can branch on *what* went wrong instead of matching strings.
+
+use std::time::Duration;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ProxyError {
+    #[error("handshake timed out after {0:?}")]
+    HandshakeTimeout(Duration),
+
+    #[error("client TLS handshake failed: {0}")]
+    ServerHandshake(#[source] std::io::Error),
+
+    #[error("peer rejected by allowed_identities: {0}")]
+    Unauthorized(String),
+
+    #[error("failed to connect to upstream: {0}")]
+    UpstreamConnect(#[source] std::io::Error),
+
+    #[error("upstream TLS handshake failed: {0}")]
+    UpstreamHandshake(#[source] std::io::Error),
+
+    #[error("invalid upstream address {0:?}")]
+    InvalidUpstream(String),
+
+    #[error("error piping data: {0}")]
+    Pipe(#[source] std::io::Error),
+}
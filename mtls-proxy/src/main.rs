@@ -1,7 +1,8 @@
 //! Binary entry-point for the mTLS side-car proxy.
 
 mod config;
-mod proxy; 
+mod error;
+mod proxy;
 mod tls;
 
 use anyhow::Result;
@@ -26,8 +27,9 @@ async fn main() -> Result<()> {
 
     info!("Configuration loaded from {:?}", cli.config);
     info!("Listen   : {}", cfg.listen);
-    info!("Upstream : {}", cfg.upstream);
-    info!("CA file  : {}", cfg.tls.ca_file);
+    for route in &cfg.routes {
+        info!("Route    : sni={} -> upstream={}", route.sni, route.upstream);
+    }
 
     // ── 3. Build and run the proxy ─────────────────────────────────────────────
     let proxy = proxy::Proxy::new(cfg)?;
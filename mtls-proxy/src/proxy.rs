@@ -1,32 +1,70 @@
 
 
-use std::{sync::Arc, time::Duration, future::Future};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+    future::Future,
+};
 
 use anyhow::Result;
+use arc_swap::ArcSwap;
 
 use tokio::{
     io::{copy_bidirectional, AsyncReadExt, AsyncWriteExt},
     net::{TcpListener, TcpStream},
+    sync::Semaphore,
     time::timeout,
 };
 
-use tokio_rustls::{TlsAcceptor, TlsConnector};
-use rustls::pki_types::ServerName;          
-use tracing::{error, info, warn};
+use tokio_rustls::{LazyConfigAcceptor, TlsConnector};
+use rustls::{pki_types::ServerName, server::Acceptor};
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    config::Config,
-    tls,                                       
+    config::{Config, RouteConfig, TlsConfig},
+    error::ProxyError,
+    tls,
 };
 
+/// How often the hot-reload watcher polls cert/key mtimes.
+const CERT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One virtual host: the TLS identity to present for a given SNI hostname,
+/// plus the upstream it proxies to once terminated.
+///
+/// `server_cfg`/`client_cfg` live behind an `ArcSwap` so the certificate
+/// watcher can rotate them in place — connections in flight keep the
+/// `Arc<ServerConfig>` they already loaded, new connections pick up the
+/// latest one.
+struct Route {
+    cfg: RouteConfig,
+    server_cfg: ArcSwap<rustls::ServerConfig>,
+    client_cfg: ArcSwap<rustls::ClientConfig>,
+}
+
+/// Latest modification time across a route's CA/cert/key files, used by the
+/// watcher to detect rotation. `None` if any file can't be stat'd.
+fn newest_mtime(tls: &TlsConfig) -> Option<SystemTime> {
+    [&tls.ca_file, &tls.server_cert, &tls.server_key, &tls.client_cert, &tls.client_key]
+        .into_iter()
+        .map(|path| -> std::io::Result<SystemTime> { std::fs::metadata(path)?.modified() })
+        .collect::<std::io::Result<Vec<_>>>()
+        .ok()?
+        .into_iter()
+        .max()
+}
 
 /// `Proxy` is a minimal mTLS side-car:
-/// 1. Terminates **incoming** mutual TLS from local clients.
-/// 2. Opens a fresh (m)TLS channel to an upstream service.
+/// 1. Terminates **incoming** mutual TLS from local clients, picking a
+///    [`Route`] by SNI hostname.
+/// 2. Opens a fresh (m)TLS channel to that route's upstream service.
 /// 3. Streams bytes in both directions.
 ///
-/// All runtime settings (listen addr, upstream addr, certificate paths…)
-/// are provided via a [`Config`] struct loaded from `proxy.yaml`.
+/// All runtime settings (listen addr, routes, certificate paths…) are
+/// provided via a [`Config`] struct loaded from `proxy.yaml`.
 ///
 /// All heavy objects are wrapped in `Arc`, so the `Proxy` can be cloned
 /// cheaply into every Tokio task spawned per connection.
@@ -34,9 +72,14 @@ use crate::{
 
 #[derive(Clone)]
 pub struct Proxy {
-    server_cfg: Arc<rustls::ServerConfig>,
-    client_cfg: Arc<rustls::ClientConfig>,
-    app_cfg: Config,
+    routes: Arc<Vec<Route>>,
+    listen: String,
+    /// Bounds the number of connections being handshaked/proxied at once;
+    /// the accept loop acquires a permit before spawning a connection task.
+    connection_limiter: Arc<Semaphore>,
+    /// Connections currently past the semaphore and not yet finished, for
+    /// logging only.
+    in_flight: Arc<AtomicUsize>,
 }
 
 impl Proxy {
@@ -50,7 +93,10 @@ impl Proxy {
     /// exit is **not** considered an error.
 
     pub async fn run(&self) -> Result<()> {
-        info!("Starting mTLS proxy — listen={}, upstream={}", self.app_cfg.listen, self.app_cfg.upstream);
+        info!("Starting mTLS proxy — listen={}, routes={}", self.listen, self.routes.len());
+
+        let watcher = self.clone();
+        tokio::spawn(async move { watcher.watch_for_cert_changes().await });
 
         tokio::select! {
             res = self.accept_loop() => {
@@ -63,6 +109,64 @@ impl Proxy {
         }
     }
 
+    /// Poll every route's cert/key/CA files for changes and hot-swap the
+    /// built `ServerConfig`/`ClientConfig` when they rotate, so certificate
+    /// renewal never requires a restart or drops in-flight connections.
+    ///
+    /// Runs for the lifetime of the process; a reload that fails to parse
+    /// is logged and the previous good configuration is kept in place.
+    async fn watch_for_cert_changes(&self) {
+        let mut last_seen: Vec<Option<SystemTime>> = self
+            .routes
+            .iter()
+            .map(|r| newest_mtime(&r.cfg.tls))
+            .collect();
+
+        let mut ticker = tokio::time::interval(CERT_POLL_INTERVAL);
+        ticker.tick().await; // the first tick fires immediately; skip it
+
+        loop {
+            ticker.tick().await;
+
+            for (idx, route) in self.routes.iter().enumerate() {
+                let newest = newest_mtime(&route.cfg.tls);
+                if newest.is_some() && newest != last_seen[idx] {
+                    last_seen[idx] = newest;
+                    self.reload_route(route).await;
+                }
+            }
+        }
+    }
+
+    /// Rebuild and swap in a route's TLS configuration after detecting a
+    /// cert/key change on disk.
+    async fn reload_route(&self, route: &Route) {
+        let server_result = tls::build_server_config(&route.cfg.tls);
+        let client_result = tls::build_client_config(&route.cfg.tls);
+
+        match (server_result, client_result) {
+            (Ok(server_cfg), Ok(client_cfg)) => {
+                route.server_cfg.store(Arc::new(server_cfg));
+                route.client_cfg.store(Arc::new(client_cfg));
+                info!("Reloaded TLS configuration for route sni={}", route.cfg.sni);
+            }
+            (server_result, client_result) => {
+                if let Err(e) = server_result {
+                    error!(
+                        "Failed to reload server TLS configuration for route sni={}: {} (keeping previous configuration)",
+                        route.cfg.sni, e
+                    );
+                }
+                if let Err(e) = client_result {
+                    error!(
+                        "Failed to reload client TLS configuration for route sni={}: {} (keeping previous configuration)",
+                        route.cfg.sni, e
+                    );
+                }
+            }
+        }
+    }
+
 
     /// Bind a `TcpListener`, accept incoming TCP connections, and spawn one
     /// Tokio task per client.
@@ -71,8 +175,12 @@ impl Proxy {
     /// [`handle_connection`].  The loop never returns unless the listener
     /// itself fails.
     async fn accept_loop(&self) -> anyhow::Result<()> {
-        let listener = TcpListener::bind(&self.app_cfg.listen).await?;
-        info!("Proxy listening on {}", self.app_cfg.listen);
+        let listener = TcpListener::bind(&self.listen).await?;
+        info!(
+            "Proxy listening on {} (max_concurrent_connections={})",
+            self.listen,
+            self.connection_limiter.available_permits()
+        );
 
         loop {
             let (sock,peer_addr) = match listener.accept().await {
@@ -82,73 +190,178 @@ impl Proxy {
                     continue;
                 }
             };
+
+            // Backpressure: block the accept loop until a handshake slot
+            // frees up rather than spawning (and handshaking) unbounded.
+            let permit = match self.connection_limiter.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => unreachable!("connection_limiter semaphore is never closed"),
+            };
+            let in_flight = self.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+            info!("Accepted connection from {} (in_flight={})", peer_addr, in_flight);
+
             let proxy = self.clone();
 
             tokio::spawn(async move {
                 if let Err(e) = proxy.handle_connection(sock).await {
                     error!("Connection from {} ended with error {:?}", peer_addr, e);
                 }
+                proxy.in_flight.fetch_sub(1, Ordering::Relaxed);
+                drop(permit);
             });
         }
 
     }
 
-    /// Perform the **server-side** mTLS handshake for an inbound socket.
+    /// Pick the [`Route`] matching a ClientHello's SNI hostname.
+    ///
+    /// Falls back to the route configured with `sni: "*"` (if any) when the
+    /// client sent no SNI, or sent one that matched nothing else.
+    fn select_route(&self, sni: Option<&str>) -> Option<usize> {
+        if let Some(name) = sni {
+            if let Some(idx) = self.routes.iter().position(|r| r.cfg.sni == name) {
+                return Some(idx);
+            }
+        }
+        self.routes.iter().position(|r| r.cfg.sni == "*")
+    }
+
+    /// Perform the **server-side** mTLS handshake for an inbound socket,
+    /// choosing the route's certificate by SNI.
     ///
-    /// * Requires a valid **client certificate** (via
-    ///   `rustls::AllowAnyAuthenticatedClient`).
-    /// * ALPN is fixed to `h2`.
+    /// Uses `LazyConfigAcceptor` so the `ServerConfig` (and therefore the
+    /// cert/key pair) can be selected *after* peeking at the ClientHello,
+    /// which is what makes multiple virtual hosts on one listener possible.
     ///
     /// # Parameters
-    /// * `raw_conn` – the raw `TcpStream` accepted by the listener.
+    /// * `raw_conn` – the raw `TcpStream` accepted by the listener, borrowed
+    ///   so that a failed/timed-out handshake can still be shut down
+    ///   explicitly instead of just dropped (see the error handling below).
     ///
     /// # Returns
-    /// An authenticated, encrypted `TlsStream`.
+    /// The authenticated, encrypted `TlsStream` and the index of the
+    /// [`Route`] that was selected.
     ///
     /// # Errors
-    /// Times out after 5 s via [`with_timeout`] or returns any rustls / I/O
-    /// error produced during the handshake.
-    async fn tls_accept(&self, raw_conn: TcpStream) -> anyhow::Result<tokio_rustls::server::TlsStream<TcpStream>>{
-        let acceptor = TlsAcceptor::from(self.server_cfg.clone());
+    /// Times out after 5 s via [`with_timeout`], fails with a
+    /// `ServerHandshake` error tagged `unrecognized_name` when no route
+    /// matches the SNI (no TLS alert is sent; the socket is just shut down),
+    /// or returns any rustls / I/O error produced during the handshake. On every error path
+    /// `raw_conn` is shut down before returning, so a slow or malicious peer
+    /// gets an orderly FIN/RST instead of leaving the socket to `Drop`
+    /// (which can leave it in `CLOSE_WAIT` if unread bytes remain).
+    async fn tls_accept<'a>(&self, raw_conn: &'a mut TcpStream) -> Result<(tokio_rustls::server::TlsStream<&'a mut TcpStream>, usize), ProxyError> {
         let handshake = async {
-            acceptor.accept(raw_conn).await.map_err(anyhow::Error::from)
+            let start = LazyConfigAcceptor::new(Acceptor::default(), &mut *raw_conn)
+                .await
+                .map_err(ProxyError::ServerHandshake)?;
+
+            let sni = start.client_hello().server_name().map(str::to_owned);
+            let idx = self.select_route(sni.as_deref()).ok_or_else(|| {
+                ProxyError::ServerHandshake(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unrecognized_name: no route for SNI {:?}", sni),
+                ))
+            })?;
+
+            let stream = start
+                .into_stream(self.routes[idx].server_cfg.load_full())
+                .await
+                .map_err(ProxyError::ServerHandshake)?;
+
+            Ok((stream, idx))
         };
 
-        let stream = self.with_timeout(handshake, Duration::from_secs(5)).await?;
-
-        Ok(stream)
+        match self.with_timeout(handshake, Duration::from_secs(5)).await {
+            Ok(accepted) => Ok(accepted),
+            Err(e) => {
+                let _ = raw_conn.shutdown().await;
+                Err(e)
+            }
+        }
     }
 
 
-    /// Dial the upstream address with a **client-side** (m)TLS handshake.
+    /// Check the authenticated client's leaf certificate identity against
+    /// the route's `tls.allowed_identities`.
+    ///
+    /// # Errors
+    /// Returns an error (and leaves the connection for the caller to close)
+    /// if the peer presented no certificate or none of its identities match
+    /// the allowlist. An empty allowlist accepts any cert that chains to the
+    /// configured CA, matching the pre-existing behaviour.
+    fn authorize_peer<IO>(&self, stream: &tokio_rustls::server::TlsStream<IO>, route: &Route) -> anyhow::Result<()> {
+        let (_, conn) = stream.get_ref();
+        let leaf = conn
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .ok_or_else(|| anyhow::anyhow!("no peer certificate presented"))?;
+
+        let identity = tls::authorize_peer(leaf, &route.cfg.tls.allowed_identities)?;
+        info!("Authorized peer identity: {} (route sni={})", identity, route.cfg.sni);
+        Ok(())
+    }
+
+    /// Dial a route's upstream address with a **client-side** (m)TLS
+    /// handshake.
     ///
     /// Presents the proxy’s client certificate and validates the upstream
-    /// server certificate against the configured CA.
+    /// server certificate against the configured CA. `downstream_alpn`, if
+    /// set, is the protocol already negotiated with the client; when
+    /// present it narrows the offer to just that protocol so the upstream
+    /// can't negotiate something the client never agreed to.
     ///
     /// # Returns
     /// A fully negotiated `TlsStream<TcpStream>` ready for proxying.
     ///
     /// # Errors
-    /// * Invalid `upstream` string (must be `host:port`).
+    /// * Invalid `upstream` string — see [`parse_upstream`] for the accepted
+    ///   forms and its distinct "no port" vs "unparseable" errors.
     /// * Timeout after 10 s.
     /// * Any rustls / I/O error during the handshake.
-    async fn connect_upstream(&self) -> anyhow::Result<tokio_rustls::client::TlsStream<TcpStream>> {
-        let connector = TlsConnector::from(self.client_cfg.clone());
-        let tcp_stream = TcpStream::connect(&self.app_cfg.upstream).await?;
+    async fn connect_upstream(
+        &self,
+        route: &Route,
+        downstream_alpn: Option<Vec<u8>>,
+    ) -> Result<tokio_rustls::client::TlsStream<TcpStream>, ProxyError> {
+        let mut client_cfg = (*route.client_cfg.load_full()).clone();
+        if let Some(proto) = &downstream_alpn {
+            client_cfg.alpn_protocols = vec![proto.clone()];
+        }
+        let connector = TlsConnector::from(Arc::new(client_cfg));
 
-       
-        let host = self.app_cfg.upstream.split(":").next().ok_or_else(|| anyhow::anyhow!("invalid upstream address"))?.to_owned();
+        let (host, port) = parse_upstream(&route.cfg.upstream)?;
+        let connect_addr = if host.contains(':') {
+            format!("[{host}]:{port}") // bracket back up for an IPv6 literal
+        } else {
+            format!("{host}:{port}")
+        };
+        let tcp_stream = TcpStream::connect(&connect_addr)
+            .await
+            .map_err(ProxyError::UpstreamConnect)?;
 
-        let server_name = ServerName::try_from(host)
-            .map_err(|_| anyhow::anyhow!("invalid ServerName for upsream"))?;
+        let sni_host = route.cfg.tls.upstream_sni.clone().unwrap_or(host);
+        let server_name = ServerName::try_from(sni_host)
+            .map_err(|_| ProxyError::InvalidUpstream(route.cfg.upstream.clone()))?;
         let handshake = async {
-            connector.connect(server_name, tcp_stream).await.map_err(anyhow::Error::from)
+            connector.connect(server_name, tcp_stream).await.map_err(ProxyError::UpstreamHandshake)
         };
 
-        self.with_timeout(handshake, Duration::from_secs(10)).await
+        let stream = self.with_timeout(handshake, Duration::from_secs(10)).await?;
 
+        let upstream_alpn = stream.get_ref().1.alpn_protocol().map(|p| p.to_vec());
+        info!("Upstream negotiated ALPN protocol: {:?}", upstream_alpn.as_deref().map(String::from_utf8_lossy));
+        if downstream_alpn.is_some() && upstream_alpn != downstream_alpn {
+            warn!(
+                "ALPN mismatch: downstream negotiated {:?}, upstream negotiated {:?}",
+                downstream_alpn.as_deref().map(String::from_utf8_lossy),
+                upstream_alpn.as_deref().map(String::from_utf8_lossy),
+            );
+        }
+
+        Ok(stream)
     }
-    
+
     /// Bi-directional byte pump between client and server.
     ///
     /// Wraps `tokio::io::copy_bidirectional` and logs total byte counts when
@@ -157,10 +370,10 @@ impl Proxy {
     /// # Errors
     /// Propagates any I/O error raised while copying.
     async fn pipe(
-        &self, 
+        &self,
         downstream: &mut (impl AsyncReadExt + AsyncWriteExt + Unpin),
         upstream: &mut (impl AsyncReadExt + AsyncWriteExt + Unpin)
-        ) -> anyhow::Result<()> {
+        ) -> Result<(), ProxyError> {
             match copy_bidirectional(downstream, upstream).await {
                 Ok((from_client, from_server)) => {
                     info!("Connection closed. Bytes from client {}, from server {}", from_client, from_server);
@@ -168,7 +381,7 @@ impl Proxy {
                 }
                 Err(e) => {
                     error!("Error with piping data {}", e);
-                    Err(e.into())
+                    Err(ProxyError::Pipe(e))
                 }
             }
 
@@ -176,45 +389,70 @@ impl Proxy {
 
     /// Build a fully-initialised [`Proxy`] from YAML configuration.
     ///
-    /// Loads certificates/keys from disk and constructs both
-    /// `rustls::ServerConfig` and `rustls::ClientConfig`.
+    /// Loads certificates/keys from disk and constructs a `ServerConfig` /
+    /// `ClientConfig` pair per route.
     ///
     /// # Errors
-    /// Returns an [`anyhow::Error`] if any file is missing or a certificate /
-    /// key fails to parse.
+    /// Returns an [`anyhow::Error`] if any file is missing, a certificate /
+    /// key fails to parse, or no routes are configured.
 
     pub fn new(cfg: Config) -> anyhow::Result<Self> {
-        let server_cfg = Arc::new(tls::build_server_config(&cfg.tls)?);
-        let client_cfg = Arc::new(tls::build_client_config(&cfg.tls)?);
-        
+        if cfg.routes.is_empty() {
+            anyhow::bail!("no routes configured");
+        }
+
+        let mut routes = Vec::with_capacity(cfg.routes.len());
+        for route_cfg in cfg.routes {
+            let server_cfg = tls::build_server_config(&route_cfg.tls)?;
+            let client_cfg = tls::build_client_config(&route_cfg.tls)?;
+            routes.push(Route {
+                cfg: route_cfg,
+                server_cfg: ArcSwap::from_pointee(server_cfg),
+                client_cfg: ArcSwap::from_pointee(client_cfg),
+            });
+        }
+
         Ok(Proxy {
-            server_cfg,
-            client_cfg,
-            app_cfg: cfg,
+            routes: Arc::new(routes),
+            listen: cfg.listen,
+            connection_limiter: Arc::new(Semaphore::new(cfg.max_concurrent_connections)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
         })
     }
 
     /// Handle one client session end-to-end:
-    /// 1. Server-side mTLS via [`tls_accept`].
-    /// 2. Client-side (m)TLS via [`connect_upstream`].
+    /// 1. Server-side mTLS + route selection via [`tls_accept`].
+    /// 2. Client-side (m)TLS to that route's upstream via [`connect_upstream`].
     /// 3. Stream bytes via [`pipe`].
     ///
     /// All per-connection errors are returned so the caller can log them.
-    async fn handle_connection(&self, incoming: TcpStream) -> Result<()>{
+    async fn handle_connection(&self, mut incoming: TcpStream) -> Result<(), ProxyError> {
 
-        let mut downstream = match self.tls_accept(incoming).await {
+        let (mut downstream, idx) = match self.tls_accept(&mut incoming).await {
             Ok(s) => s,
             Err(e) => {
-                warn!("Client TLS handshake failed {}", e);
+                log_connection_error(&e);
                 return Err(e);
             }
 
         };
+        let route = &self.routes[idx];
+
+        if let Err(e) = self.authorize_peer(&downstream, route) {
+            let _ = downstream.shutdown().await;
+            let err = ProxyError::Unauthorized(e.to_string());
+            log_connection_error(&err);
+            return Err(err);
+        }
 
-        let mut upstream = match self.connect_upstream().await {
+        let downstream_alpn = downstream.get_ref().1.alpn_protocol().map(|p| p.to_vec());
+        info!("Downstream negotiated ALPN protocol: {:?}", downstream_alpn.as_deref().map(String::from_utf8_lossy));
+
+        let mut upstream = match self.connect_upstream(route, downstream_alpn).await {
             Ok(s) => s,
             Err(e) => {
-                warn!("Failed to connect to upstream {}", e);
+                log_connection_error(&e);
+                let _ = downstream.shutdown().await;
                 return Err(e);
             }
         };
@@ -226,24 +464,147 @@ impl Proxy {
     /// Run an asynchronous operation with a hard deadline.
     ///
     /// # Parameters
-    /// * `fut` – any `Future` that returns `anyhow::Result<T>`.
+    /// * `fut` – any `Future` that returns `Result<T, ProxyError>`.
     /// * `dur` – maximum duration to wait.
     ///
     /// # Returns
     /// The inner success value if the future completes in time.
     ///
     /// # Errors
-    /// * `anyhow!("Operation timed out …")` if the deadline is exceeded.
+    /// * `ProxyError::HandshakeTimeout` if the deadline is exceeded.
     /// * Any underlying error produced by `fut`.
     async fn with_timeout<F, T> (
         &self,
         fut: F,
         dur: Duration,
-    ) -> anyhow::Result<T> where F: Future<Output = anyhow::Result<T>> {
+    ) -> Result<T, ProxyError> where F: Future<Output = Result<T, ProxyError>> {
 
         match timeout(dur, fut).await {
             Ok(inner_res) => inner_res,
-            Err(_) => Err(anyhow::anyhow!("Operation timed out after {:?}", dur)),
+            Err(_) => Err(ProxyError::HandshakeTimeout(dur)),
+        }
+    }
+}
+
+/// Default port assumed for an `upstream` address given as a bare hostname
+/// behind an `https://` scheme, with no explicit port.
+const DEFAULT_UPSTREAM_PORT: u16 = 443;
+
+/// Parse an `upstream` address into a connect host and port, accepting
+/// `host:port`, a bracketed IPv6 literal (`[::1]:9443`), and an optional
+/// `https://` scheme (defaulting to port 443 when the scheme is given
+/// without one). A bare `host` with no port and no scheme is rejected with a
+/// distinct error from an unparseable port/host, so misconfigurations are
+/// easy to tell apart in logs.
+fn parse_upstream(addr: &str) -> Result<(String, u16), ProxyError> {
+    let had_scheme = addr.starts_with("https://");
+    let rest = addr.strip_prefix("https://").unwrap_or(addr);
+
+    if let Some(after_bracket) = rest.strip_prefix('[') {
+        let (host, after_host) = after_bracket.split_once(']').ok_or_else(|| {
+            ProxyError::InvalidUpstream(format!("{addr}: unterminated IPv6 literal"))
+        })?;
+        let port = match after_host.strip_prefix(':') {
+            Some(p) if !p.is_empty() => p.parse().map_err(|_| {
+                ProxyError::InvalidUpstream(format!("{addr}: unparseable port {p:?}"))
+            })?,
+            Some(_) => {
+                return Err(ProxyError::InvalidUpstream(format!("{addr}: no port after ':'")))
+            }
+            None if had_scheme => DEFAULT_UPSTREAM_PORT,
+            None => {
+                return Err(ProxyError::InvalidUpstream(format!(
+                    "{addr}: missing port; expected host:port"
+                )))
+            }
+        };
+        return Ok((host.to_string(), port));
+    }
+
+    match rest.rsplit_once(':') {
+        Some((host, port)) if !port.is_empty() => {
+            let port = port.parse().map_err(|_| {
+                ProxyError::InvalidUpstream(format!("{addr}: unparseable port {port:?}"))
+            })?;
+            Ok((host.to_string(), port))
+        }
+        Some(_) => Err(ProxyError::InvalidUpstream(format!("{addr}: no port after ':'"))),
+        None if had_scheme => Ok((rest.to_string(), DEFAULT_UPSTREAM_PORT)),
+        None => Err(ProxyError::InvalidUpstream(format!(
+            "{addr}: missing port; expected host:port"
+        ))),
+    }
+}
+
+/// Log a per-connection failure at a level/target matched to its class: a
+/// clean client disconnect mid-handshake is expected noise (`debug`), while
+/// everything else is worth a `warn`/`error`.
+fn log_connection_error(err: &ProxyError) {
+    match err {
+        ProxyError::ServerHandshake(io_err) if io_err.kind() == std::io::ErrorKind::UnexpectedEof => {
+            debug!("Client disconnected mid-handshake: {}", io_err);
         }
+        ProxyError::ServerHandshake(e) => warn!("Client TLS handshake failed: {}", e),
+        ProxyError::Unauthorized(reason) => warn!("Rejecting unauthorized peer: {}", reason),
+        ProxyError::HandshakeTimeout(dur) => warn!("Handshake timed out after {:?}", dur),
+        ProxyError::UpstreamConnect(e) => warn!("Failed to connect to upstream: {}", e),
+        ProxyError::UpstreamHandshake(e) => warn!("Upstream TLS handshake failed: {}", e),
+        ProxyError::InvalidUpstream(addr) => warn!("Invalid upstream address: {}", addr),
+        ProxyError::Pipe(e) => error!("Error piping data: {}", e),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_upstream;
+
+    #[test]
+    fn host_port() {
+        assert_eq!(parse_upstream("example.com:9443").unwrap(), ("example.com".to_string(), 9443));
+    }
+
+    #[test]
+    fn bare_host_with_no_port_is_an_error() {
+        assert!(parse_upstream("example.com").is_err());
+    }
+
+    #[test]
+    fn scheme_with_no_port_defaults_to_443() {
+        assert_eq!(parse_upstream("https://example.com").unwrap(), ("example.com".to_string(), 443));
+    }
+
+    #[test]
+    fn scheme_with_explicit_port() {
+        assert_eq!(parse_upstream("https://example.com:8443").unwrap(), ("example.com".to_string(), 8443));
+    }
+
+    #[test]
+    fn bracketed_ipv6_with_port() {
+        assert_eq!(parse_upstream("[::1]:9443").unwrap(), ("::1".to_string(), 9443));
+    }
+
+    #[test]
+    fn bracketed_ipv6_with_no_port_and_no_scheme_is_an_error() {
+        assert!(parse_upstream("[::1]").is_err());
+    }
+
+    #[test]
+    fn bracketed_ipv6_with_scheme_and_no_port_defaults_to_443() {
+        assert_eq!(parse_upstream("https://[::1]").unwrap(), ("::1".to_string(), 443));
+    }
+
+    #[test]
+    fn unterminated_ipv6_literal_is_an_error() {
+        assert!(parse_upstream("[::1:9443").is_err());
+    }
+
+    #[test]
+    fn trailing_colon_with_no_port_is_an_error() {
+        assert!(parse_upstream("example.com:").is_err());
+    }
+
+    #[test]
+    fn unparseable_port_is_an_error() {
+        assert!(parse_upstream("example.com:notaport").is_err());
+    }
+}
@@ -19,6 +19,7 @@ use rustls_pemfile::{
     pkcs8_private_keys,
 };
 use anyhow::Result;
+use x509_parser::prelude::*;
 use crate::config::TlsConfig;
 
 pub fn cert_reader<P: AsRef<Path>>(cert_path: P) -> anyhow::Result<Vec<CertificateDer<'static>>> {
@@ -58,6 +59,82 @@ pub fn load_root_store<P: AsRef<Path>>(ca_path: P) -> anyhow::Result<RootCertSto
 
 }
 
+/// Pull the SAN `dNSName` entries out of a leaf certificate, falling back to
+/// the subject CN when no SAN `dNSName` entry is found (no SAN extension, an
+/// unparseable one, or one holding only other name types).
+fn peer_identities(leaf: &CertificateDer<'_>) -> anyhow::Result<Vec<String>> {
+    let (_, cert) = X509Certificate::from_der(leaf.as_ref())
+        .map_err(|e| anyhow::anyhow!("failed to parse peer certificate: {e}"))?;
+
+    let mut identities: Vec<String> = cert
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|san| {
+            san.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(dns) => Some((*dns).to_owned()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if identities.is_empty() {
+        if let Some(cn) = cert.subject().iter_common_name().next() {
+            identities.push(cn.as_str()?.to_owned());
+        }
+    }
+
+    Ok(identities)
+}
+
+/// Match an `allowed_identities` pattern against a peer identity, supporting
+/// an exact match or a single leading wildcard label (`*.example.com`). DNS
+/// names are case-insensitive, so both sides are compared ASCII-lowercased.
+fn identity_matches(pattern: &str, identity: &str) -> bool {
+    let identity = identity.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => identity
+            .strip_suffix(&domain.to_ascii_lowercase())
+            .map(|prefix| {
+                prefix.len() > 1 && prefix.ends_with('.') && !prefix[..prefix.len() - 1].contains('.')
+            })
+            .unwrap_or(false),
+        None => pattern.eq_ignore_ascii_case(&identity),
+    }
+}
+
+/// Verify a client's leaf certificate identity against `allowed`, returning
+/// the matched identity. An empty allowlist accepts any authenticated peer,
+/// preserving the "valid chain is enough" behaviour.
+pub fn authorize_peer(leaf: &CertificateDer<'_>, allowed: &[String]) -> anyhow::Result<String> {
+    if allowed.is_empty() {
+        return Ok("<any authenticated peer>".to_string());
+    }
+
+    let identities = peer_identities(leaf)?;
+    for identity in &identities {
+        if allowed.iter().any(|pattern| identity_matches(pattern, identity)) {
+            return Ok(identity.clone());
+        }
+    }
+
+    anyhow::bail!(
+        "peer identity {:?} not in allowed_identities {:?}",
+        identities,
+        allowed
+    )
+}
+
+/// Turn `tls.alpn` (e.g. `["h2", "http/1.1"]`) into the wire-format protocol
+/// list `rustls::ServerConfig`/`ClientConfig` expect.
+fn alpn_protocols(tls: &TlsConfig) -> Vec<Vec<u8>> {
+    tls.alpn.iter().map(|p| p.as_bytes().to_vec()).collect()
+}
+
 pub fn build_server_config(tls: &TlsConfig) -> Result<ServerConfig> {
     let server_cert = cert_reader(&tls.server_cert)?;
     let privkey_server = privkey_reader(&tls.server_key)?;
@@ -69,27 +146,67 @@ pub fn build_server_config(tls: &TlsConfig) -> Result<ServerConfig> {
     .with_client_cert_verifier(client_verifier)
     .with_single_cert(server_cert, privkey_server)?;
 
-    config.alpn_protocols = vec![b"h2".to_vec()];
+    config.alpn_protocols = alpn_protocols(tls);
 
     Ok(config)
 }
 
 
-    
+
 pub fn build_client_config(tls: &TlsConfig) -> Result<ClientConfig> {
     let client_cert = cert_reader(&tls.client_cert)?;
     let privkey_client = privkey_reader(&tls.client_key)?;
     let root_store = load_root_store(&tls.ca_file)?;
 
-    
+
 
     let mut config = ClientConfig::builder()
     .with_root_certificates(root_store)
     .with_client_auth_cert(client_cert, privkey_client)?;
 
-    config.alpn_protocols = vec![b"h2".to_vec()];
+    config.alpn_protocols = alpn_protocols(tls);
 
     Ok(config)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::identity_matches;
+
+    #[test]
+    fn exact_match() {
+        assert!(identity_matches("client.example.com", "client.example.com"));
+    }
+
+    #[test]
+    fn exact_match_is_case_insensitive() {
+        assert!(identity_matches("Client.Example.com", "client.example.com"));
+    }
+
+    #[test]
+    fn exact_match_rejects_different_host() {
+        assert!(!identity_matches("other.example.com", "client.example.com"));
+    }
+
+    #[test]
+    fn wildcard_matches_single_label() {
+        assert!(identity_matches("*.example.com", "foo.example.com"));
+    }
+
+    #[test]
+    fn wildcard_match_is_case_insensitive_on_both_sides() {
+        assert!(identity_matches("*.Example.com", "Foo.example.com"));
+        assert!(identity_matches("*.example.com", "FOO.EXAMPLE.COM"));
+    }
+
+    #[test]
+    fn wildcard_does_not_match_bare_domain() {
+        assert!(!identity_matches("*.example.com", "example.com"));
+    }
+
+    #[test]
+    fn wildcard_does_not_match_multiple_labels() {
+        assert!(!identity_matches("*.example.com", "foo.bar.example.com"));
+    }
+}
 